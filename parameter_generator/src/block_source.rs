@@ -0,0 +1,154 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use bitcoin::hashes::Hash;
+use bitcoin::{consensus::deserialize, Block, BlockHash};
+use reqwest::Client;
+
+/// A source of Bitcoin block data. Lets the CLI fetch the block containing a
+/// transaction, and the current chain tip height, without hard-coding trust
+/// in a single third-party API.
+#[async_trait]
+pub trait BlockSource {
+    async fn block_for_tx(&self, txid: &str) -> Result<(Block, BlockHash), Box<dyn Error>>;
+    async fn block_at_height(&self, height: u64) -> Result<(Block, BlockHash), Box<dyn Error>>;
+    async fn tip_height(&self) -> Result<u64, Box<dyn Error>>;
+    async fn block_height(&self, block_hash: &BlockHash) -> Result<u64, Box<dyn Error>>;
+}
+
+/// Fetches blocks from an Esplora-compatible REST API (e.g. blockstream.info).
+pub struct EsploraSource {
+    client: Client,
+    base_url: String,
+}
+
+impl EsploraSource {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+        }
+    }
+
+    async fn block_by_hash(&self, block_hash: &str) -> Result<(Block, BlockHash), Box<dyn Error>> {
+        let url = format!("{}/api/block/{}/raw", self.base_url, block_hash);
+        let block_bytes = self.client.get(&url).send().await?.bytes().await?.to_vec();
+        let block: Block = deserialize(&block_bytes)?;
+        let block_hash = block.header.block_hash();
+        Ok((block, block_hash))
+    }
+}
+
+#[async_trait]
+impl BlockSource for EsploraSource {
+    async fn block_for_tx(&self, txid: &str) -> Result<(Block, BlockHash), Box<dyn Error>> {
+        let url = format!("{}/api/tx/{}", self.base_url, txid);
+        let tx_json = self.client.get(&url).send().await?.json::<serde_json::Value>().await?;
+        let block_hash = tx_json["status"]["block_hash"]
+            .as_str()
+            .ok_or("transaction is not confirmed in a block")?;
+        self.block_by_hash(block_hash).await
+    }
+
+    async fn block_at_height(&self, height: u64) -> Result<(Block, BlockHash), Box<dyn Error>> {
+        let url = format!("{}/api/block-height/{}", self.base_url, height);
+        let block_hash = self.client.get(&url).send().await?.text().await?;
+        self.block_by_hash(block_hash.trim()).await
+    }
+
+    async fn tip_height(&self) -> Result<u64, Box<dyn Error>> {
+        let url = format!("{}/api/blocks/tip/height", self.base_url);
+        let height = self.client.get(&url).send().await?.text().await?;
+        Ok(height.trim().parse()?)
+    }
+
+    async fn block_height(&self, block_hash: &BlockHash) -> Result<u64, Box<dyn Error>> {
+        let url = format!("{}/api/block/{}", self.base_url, block_hash);
+        let block_json = self.client.get(&url).send().await?.json::<serde_json::Value>().await?;
+        block_json["height"].as_u64().ok_or_else(|| "unexpected block status response".into())
+    }
+}
+
+/// Fetches blocks from a Bitcoin Core node over its JSON-RPC interface,
+/// authenticating with a `.cookie` file the way `bitcoin-cli` does.
+pub struct CoreRpcSource {
+    client: Client,
+    rpc_url: String,
+    rpc_cookie: String,
+}
+
+impl CoreRpcSource {
+    pub fn new(rpc_url: String, rpc_cookie: String) -> Self {
+        Self {
+            client: Client::new(),
+            rpc_url,
+            rpc_cookie,
+        }
+    }
+
+    async fn call(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value, Box<dyn Error>> {
+        let cookie = std::fs::read_to_string(&self.rpc_cookie)?;
+        let (user, password) = cookie
+            .trim()
+            .split_once(':')
+            .ok_or("rpc cookie file is not in `user:password` format")?;
+
+        let body = serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "parameter_generator",
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .client
+            .post(&self.rpc_url)
+            .basic_auth(user, Some(password))
+            .json(&body)
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+
+        if !response["error"].is_null() {
+            return Err(format!("bitcoind RPC {} failed: {}", method, response["error"]).into());
+        }
+
+        Ok(response["result"].clone())
+    }
+
+    async fn block_by_hash(&self, block_hash: &str) -> Result<(Block, BlockHash), Box<dyn Error>> {
+        let block_hex = self.call("getblock", serde_json::json!([block_hash, 0])).await?;
+        let block_bytes = hex::decode(block_hex.as_str().ok_or("unexpected getblock response")?)?;
+        let block: Block = deserialize(&block_bytes)?;
+        let block_hash = block.header.block_hash();
+        Ok((block, block_hash))
+    }
+}
+
+#[async_trait]
+impl BlockSource for CoreRpcSource {
+    async fn block_for_tx(&self, txid: &str) -> Result<(Block, BlockHash), Box<dyn Error>> {
+        let verbose_tx = self.call("getrawtransaction", serde_json::json!([txid, true])).await?;
+        let block_hash = verbose_tx["blockhash"]
+            .as_str()
+            .ok_or("transaction is not confirmed in a block")?;
+        self.block_by_hash(block_hash).await
+    }
+
+    async fn block_at_height(&self, height: u64) -> Result<(Block, BlockHash), Box<dyn Error>> {
+        let block_hash = self.call("getblockhash", serde_json::json!([height])).await?;
+        let block_hash = block_hash.as_str().ok_or("unexpected getblockhash response")?;
+        self.block_by_hash(block_hash).await
+    }
+
+    async fn tip_height(&self) -> Result<u64, Box<dyn Error>> {
+        let height = self.call("getblockcount", serde_json::json!([])).await?;
+        height.as_u64().ok_or_else(|| "unexpected getblockcount response".into())
+    }
+
+    async fn block_height(&self, block_hash: &BlockHash) -> Result<u64, Box<dyn Error>> {
+        let header = self.call("getblockheader", serde_json::json!([block_hash.to_string()])).await?;
+        header["height"].as_u64().ok_or_else(|| "unexpected getblockheader response".into())
+    }
+}